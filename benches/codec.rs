@@ -0,0 +1,96 @@
+//! Compares the JSON and bincode codecs on the two things a running log
+//! actually does with a codec: encoding on every `append_event` call, and
+//! decoding for every record a scan walks past. Only the codec work is
+//! timed; no file is written or read. Run
+//! with `cargo bench --features codec-bincode` to see both; without the
+//! feature only the default JSON codec's numbers show up.
+
+use chronicle::codec::{Codec, JsonCodec};
+use chronicle::event::{Event, StoredEvent};
+use chronicle::hash;
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::json;
+
+const BATCH_SIZE: usize = 256;
+
+fn mock_stored_event(aggregate_id: u64) -> StoredEvent {
+    StoredEvent {
+        write_timestamp_ms: 1_700_000_000_000,
+        prev_hash: hash::ZERO_HASH,
+        event: Event {
+            event_type: "Updated".into(),
+            namespace: "accounts".into(),
+            schema_id: "Person".into(),
+            schema_version: 1,
+            aggregate_id: Some(aggregate_id),
+            payload: json!({
+                "name": "Ada Lovelace",
+                "email": "ada@example.com",
+                "active": true,
+                "tags": ["founder", "mathematician"],
+            }),
+        },
+    }
+}
+
+/// Encodes a batch of records, the per-event cost `append_event` pays on
+/// every write.
+fn bench_append_throughput(c: &mut Criterion) {
+    let batch: Vec<StoredEvent> = (0..BATCH_SIZE as u64).map(mock_stored_event).collect();
+
+    let mut group = c.benchmark_group("append_throughput");
+    group.throughput(criterion::Throughput::Elements(BATCH_SIZE as u64));
+    group.bench_function("json", |b| {
+        b.iter(|| {
+            for event in &batch {
+                criterion::black_box(JsonCodec::encode(event));
+            }
+        })
+    });
+    #[cfg(feature = "codec-bincode")]
+    group.bench_function("bincode", |b| {
+        use chronicle::codec::BincodeCodec;
+        b.iter(|| {
+            for event in &batch {
+                criterion::black_box(BincodeCodec::encode(event));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Decodes a batch of already-encoded records, the per-event cost
+/// `scan_log_entries` pays walking a segment.
+fn bench_scan_throughput(c: &mut Criterion) {
+    let json_batch: Vec<Vec<u8>> = (0..BATCH_SIZE as u64)
+        .map(|id| JsonCodec::encode(&mock_stored_event(id)))
+        .collect();
+
+    let mut group = c.benchmark_group("scan_throughput");
+    group.throughput(criterion::Throughput::Elements(BATCH_SIZE as u64));
+    group.bench_function("json", |b| {
+        b.iter(|| {
+            for bytes in &json_batch {
+                criterion::black_box(JsonCodec::decode(bytes).unwrap());
+            }
+        })
+    });
+    #[cfg(feature = "codec-bincode")]
+    {
+        use chronicle::codec::BincodeCodec;
+        let bincode_batch: Vec<Vec<u8>> = (0..BATCH_SIZE as u64)
+            .map(|id| BincodeCodec::encode(&mock_stored_event(id)))
+            .collect();
+        group.bench_function("bincode", |b| {
+            b.iter(|| {
+                for bytes in &bincode_batch {
+                    criterion::black_box(BincodeCodec::decode(bytes).unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_append_throughput, bench_scan_throughput);
+criterion_main!(benches);
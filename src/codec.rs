@@ -0,0 +1,191 @@
+//! Pluggable on-disk encoding for a single record's bytes (everything after
+//! the 4-byte length prefix; the framing itself never changes between
+//! codecs). The codec is chosen at compile time via the `codec-json`
+//! (default) and `codec-bincode` Cargo features, and recorded in a
+//! segment's [`crate::header`] flags, so a reader always knows how to
+//! decode a segment regardless of which codec the running build writes.
+
+use std::io;
+
+use crate::event::StoredEvent;
+use crate::header::HeaderFlags;
+
+/// Encodes and decodes a [`StoredEvent`]'s on-disk record bytes.
+pub trait Codec {
+    /// The header flags a segment written with this codec is tagged with.
+    const FLAGS: HeaderFlags;
+
+    fn encode(event: &StoredEvent) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> io::Result<StoredEvent>;
+}
+
+/// The default codec: human-readable JSON, and what every segment
+/// predating codec flags implicitly used.
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    const FLAGS: HeaderFlags = HeaderFlags::JSON;
+
+    fn encode(event: &StoredEvent) -> Vec<u8> {
+        serde_json::to_vec(event).expect("StoredEvent always serializes")
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<StoredEvent> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Bincode can't encode `StoredEvent` directly, for two reasons: its
+/// `#[serde(flatten)]` field forces serde to serialize as a map of unknown
+/// length, which bincode's length-prefixed format can't represent; and
+/// bincode's deserializer doesn't support `deserialize_any`, which
+/// `serde_json::Value` relies on to recover its dynamic shape. This mirrors
+/// `StoredEvent`'s fields without flattening, and carries `payload` as its
+/// compact JSON text rather than a `Value`, purely as a wire shape for
+/// [`BincodeCodec`] - the payload itself still round-trips losslessly.
+#[cfg(feature = "codec-bincode")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BincodeRecord {
+    write_timestamp_ms: u64,
+    prev_hash: crate::hash::Hash,
+    event_type: String,
+    namespace: String,
+    schema_id: String,
+    schema_version: u32,
+    aggregate_id: Option<u64>,
+    payload_json: String,
+}
+
+#[cfg(feature = "codec-bincode")]
+impl From<&StoredEvent> for BincodeRecord {
+    fn from(stored: &StoredEvent) -> Self {
+        BincodeRecord {
+            write_timestamp_ms: stored.write_timestamp_ms,
+            prev_hash: stored.prev_hash,
+            event_type: stored.event.event_type.clone(),
+            namespace: stored.event.namespace.clone(),
+            schema_id: stored.event.schema_id.clone(),
+            schema_version: stored.event.schema_version,
+            aggregate_id: stored.event.aggregate_id,
+            payload_json: stored.event.payload.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "codec-bincode")]
+impl TryFrom<BincodeRecord> for StoredEvent {
+    type Error = serde_json::Error;
+
+    fn try_from(record: BincodeRecord) -> Result<Self, Self::Error> {
+        Ok(StoredEvent {
+            write_timestamp_ms: record.write_timestamp_ms,
+            prev_hash: record.prev_hash,
+            event: crate::event::Event {
+                event_type: record.event_type,
+                namespace: record.namespace,
+                schema_id: record.schema_id,
+                schema_version: record.schema_version,
+                aggregate_id: record.aggregate_id,
+                payload: serde_json::from_str(&record.payload_json)?,
+            },
+        })
+    }
+}
+
+/// A compact binary codec, enabled by the `codec-bincode` feature for
+/// higher-throughput logs where JSON's size and parse cost dominate.
+#[cfg(feature = "codec-bincode")]
+pub struct BincodeCodec;
+
+#[cfg(feature = "codec-bincode")]
+impl Codec for BincodeCodec {
+    const FLAGS: HeaderFlags = HeaderFlags::BINCODE;
+
+    fn encode(event: &StoredEvent) -> Vec<u8> {
+        bincode::serialize(&BincodeRecord::from(event)).expect("StoredEvent always serializes")
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<StoredEvent> {
+        let record: BincodeRecord =
+            bincode::deserialize(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        StoredEvent::try_from(record).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// The codec this build writes new segments with.
+#[cfg(feature = "codec-bincode")]
+pub type ActiveCodec = BincodeCodec;
+#[cfg(not(feature = "codec-bincode"))]
+pub type ActiveCodec = JsonCodec;
+
+/// Decodes `bytes` according to whichever codec a segment's header `flags`
+/// say it was written with - not necessarily the codec this build writes -
+/// so logs stay readable across a codec feature change.
+pub fn decode(flags: HeaderFlags, bytes: &[u8]) -> io::Result<StoredEvent> {
+    if flags == JsonCodec::FLAGS {
+        return JsonCodec::decode(bytes);
+    }
+    #[cfg(feature = "codec-bincode")]
+    if flags == BincodeCodec::FLAGS {
+        return BincodeCodec::decode(bytes);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("segment uses a codec this build doesn't support (flags {flags:?})"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mock_stored_event() -> StoredEvent {
+        StoredEvent {
+            write_timestamp_ms: 42,
+            prev_hash: crate::hash::ZERO_HASH,
+            event: crate::event::Event {
+                event_type: "Test".into(),
+                namespace: "test".into(),
+                schema_id: "Test".into(),
+                schema_version: 1,
+                aggregate_id: Some(1),
+                payload: json!({"name": "string"}),
+            },
+        }
+    }
+
+    #[test]
+    fn json_codec_round_trips() {
+        let stored = mock_stored_event();
+        let bytes = JsonCodec::encode(&stored);
+        let decoded = JsonCodec::decode(&bytes).unwrap();
+        assert_eq!(decoded.to_string(), stored.to_string());
+    }
+
+    #[test]
+    fn decode_dispatches_on_header_flags() {
+        let stored = mock_stored_event();
+        let bytes = JsonCodec::encode(&stored);
+        let decoded = decode(HeaderFlags::JSON, &bytes).unwrap();
+        assert_eq!(decoded.to_string(), stored.to_string());
+    }
+
+    #[cfg(feature = "codec-bincode")]
+    #[test]
+    fn bincode_codec_round_trips_including_payload_value() {
+        let stored = mock_stored_event();
+        let bytes = BincodeCodec::encode(&stored);
+        let decoded = BincodeCodec::decode(&bytes).unwrap();
+        assert_eq!(decoded.to_string(), stored.to_string());
+    }
+
+    #[cfg(feature = "codec-bincode")]
+    #[test]
+    fn decode_dispatches_to_bincode_by_flags() {
+        let stored = mock_stored_event();
+        let bytes = BincodeCodec::encode(&stored);
+        let decoded = decode(HeaderFlags::BINCODE, &bytes).unwrap();
+        assert_eq!(decoded.to_string(), stored.to_string());
+    }
+}
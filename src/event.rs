@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fmt;
 
+use crate::hash::Hash;
+
 /// Event provided by the caller for storage.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -18,6 +20,10 @@ pub struct Event {
 pub struct StoredEvent {
     /// Timestamp set by Chronicle when the event was written.
     pub write_timestamp_ms: u64,
+    /// Chain hash of the record written immediately before this one (the
+    /// all-zero seed for the first record in the log). See
+    /// `crate::storage::verify_log`.
+    pub prev_hash: Hash,
     /// The original event data.
     #[serde(flatten)]
     pub event: Event,
@@ -0,0 +1,45 @@
+//! Chain hashes linking each stored event to the one before it, so
+//! [`crate::storage::verify_log`] can detect tampering or corruption of an
+//! otherwise well-formed record.
+
+use sha2::{Digest, Sha256};
+
+/// Length in bytes of a chain hash.
+pub const HASH_LEN: usize = 32;
+
+/// A chain hash, as produced by [`compute_hash`].
+pub type Hash = [u8; HASH_LEN];
+
+/// The hash the first record in a log chains from.
+pub const ZERO_HASH: Hash = [0u8; HASH_LEN];
+
+/// `sha256(prev_hash || len_bytes || json_bytes)`, where `len_bytes` is the
+/// record's big-endian `u32` length prefix as written to disk.
+pub fn compute_hash(prev_hash: &Hash, len: u32, json_bytes: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash);
+    hasher.update(len.to_be_bytes());
+    hasher.update(json_bytes);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_inputs_produce_the_same_hash() {
+        let a = compute_hash(&ZERO_HASH, 4, b"test");
+        let b = compute_hash(&ZERO_HASH, 4, b"test");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn different_prev_hash_changes_the_result() {
+        let a = compute_hash(&ZERO_HASH, 4, b"test");
+        let mut other_prev = ZERO_HASH;
+        other_prev[0] = 1;
+        let b = compute_hash(&other_prev, 4, b"test");
+        assert_ne!(a, b);
+    }
+}
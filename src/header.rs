@@ -0,0 +1,158 @@
+//! Versioned segment file header, so a reader can tell what encoding and
+//! schema rules govern a segment before it decodes any entries, and future
+//! format changes don't silently corrupt reads of existing `.log` files.
+//!
+//! The header is written once per segment - the first time `append_event`
+//! writes to it - not once per log, since each segment file is effectively
+//! its own "file" from a reader's perspective.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Marks the start of a versioned segment. Absence of this magic at byte 0
+/// means the segment predates headers and is read as [`FormatVersion::V0`].
+pub const MAGIC: &[u8; 8] = b"CHRONLOG";
+
+/// Total on-disk size of the header: magic, version, flags, and reserved padding.
+pub const HEADER_LEN: usize = 16;
+
+/// The only format version this build writes. Readers also understand
+/// [`FormatVersion::V0`], the headerless legacy layout, for old segments.
+pub const CURRENT_VERSION: u16 = 1;
+
+/// Codec/compression flags carried in a v1 header's single flags byte. Bit 0
+/// selects the record codec (0 = JSON, 1 = bincode); the remaining bits are
+/// reserved for future use, such as compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderFlags(pub u8);
+
+impl HeaderFlags {
+    /// Flags for the `codec-json` encoding: uncompressed JSON.
+    pub const JSON: HeaderFlags = HeaderFlags(0);
+    /// Flags for the `codec-bincode` encoding: uncompressed bincode.
+    pub const BINCODE: HeaderFlags = HeaderFlags(1);
+}
+
+/// Which format governs a segment's entries, as determined by
+/// [`read_and_validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatVersion {
+    /// The original headerless layout: entries start at byte 0, always JSON.
+    V0,
+    /// A `CHRONLOG` header precedes the entries.
+    V1 { flags: HeaderFlags },
+}
+
+impl FormatVersion {
+    /// The codec flags in effect for this segment: whatever a v1 header
+    /// carries, or [`HeaderFlags::JSON`] for a legacy headerless v0 segment.
+    pub fn flags(&self) -> HeaderFlags {
+        match self {
+            FormatVersion::V0 => HeaderFlags::JSON,
+            FormatVersion::V1 { flags } => *flags,
+        }
+    }
+}
+
+/// Encodes a fresh v1 header: magic, [`CURRENT_VERSION`], `flags`, and
+/// zeroed reserved padding.
+pub fn encode(flags: HeaderFlags) -> [u8; HEADER_LEN] {
+    let mut buf = [0u8; HEADER_LEN];
+    buf[0..8].copy_from_slice(MAGIC);
+    buf[8..10].copy_from_slice(&CURRENT_VERSION.to_be_bytes());
+    buf[10] = flags.0;
+    // buf[11..16] left zeroed: reserved for future use.
+    buf
+}
+
+/// Reads up to `buf.len()` bytes, looping over short reads, and returns how
+/// many bytes were actually available before EOF.
+fn read_up_to(file: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+/// Peeks at the start of `file` and reports which format governs the rest of
+/// it. On return, the cursor is positioned right after the header for v1, or
+/// rewound to byte 0 for v0, so the caller can start reading entries
+/// immediately.
+pub fn read_and_validate(file: &mut (impl Read + Seek)) -> io::Result<FormatVersion> {
+    let mut probe = [0u8; HEADER_LEN];
+    let read = read_up_to(file, &mut probe)?;
+
+    if read < MAGIC.len() || &probe[0..8] != MAGIC {
+        // No magic (or not even enough bytes for it): a legacy v0 segment,
+        // or an empty one. Either way entries start at byte 0.
+        file.seek(SeekFrom::Start(0))?;
+        return Ok(FormatVersion::V0);
+    }
+
+    if read < HEADER_LEN {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated CHRONLOG header"));
+    }
+
+    let version = u16::from_be_bytes([probe[8], probe[9]]);
+    if version != CURRENT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported CHRONLOG format version {version}"),
+        ));
+    }
+
+    Ok(FormatVersion::V1 {
+        flags: HeaderFlags(probe[10]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn empty_file_is_treated_as_v0() {
+        let mut cursor = Cursor::new(Vec::new());
+        assert_eq!(read_and_validate(&mut cursor).unwrap(), FormatVersion::V0);
+        assert_eq!(cursor.stream_position().unwrap(), 0);
+    }
+
+    #[test]
+    fn file_without_magic_is_treated_as_v0_and_rewound() {
+        let mut cursor = Cursor::new(b"not a chronicle segment".to_vec());
+        assert_eq!(read_and_validate(&mut cursor).unwrap(), FormatVersion::V0);
+        assert_eq!(cursor.stream_position().unwrap(), 0);
+    }
+
+    #[test]
+    fn encoded_header_round_trips_and_leaves_cursor_after_it() {
+        let mut bytes = encode(HeaderFlags::JSON).to_vec();
+        bytes.extend_from_slice(b"trailing entry bytes");
+        let mut cursor = Cursor::new(bytes);
+
+        let format = read_and_validate(&mut cursor).unwrap();
+        assert_eq!(format, FormatVersion::V1 { flags: HeaderFlags::JSON });
+        assert_eq!(cursor.stream_position().unwrap(), HEADER_LEN as u64);
+    }
+
+    #[test]
+    fn rejects_an_unsupported_future_version() {
+        let mut buf = encode(HeaderFlags::JSON);
+        buf[8..10].copy_from_slice(&99u16.to_be_bytes());
+        let mut cursor = Cursor::new(buf.to_vec());
+
+        let err = read_and_validate(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_a_truncated_header() {
+        let mut cursor = Cursor::new(MAGIC.to_vec());
+        let err = read_and_validate(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
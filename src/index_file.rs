@@ -0,0 +1,363 @@
+//! On-disk companion index for a log, so opening it doesn't require
+//! rescanning every event to locate an aggregate's offsets.
+//!
+//! Each `append_event` call appends one fixed-width [`IndexRecord`] here,
+//! in lock-step with the data write it describes. Because every append -
+//! whether or not the event carries an `aggregate_id` - gets a record, the
+//! index's last record always reflects exactly how many bytes of the
+//! active segment have been indexed, which is what [`load_index`] uses to
+//! detect a stale index without rescanning the log.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::segment::{list_segments, segment_path, SegmentId};
+use crate::storage::{scan_log_entries, AggregateIndex};
+
+/// `has_aggregate: u8 | aggregate_id: u64 | segment_id: u32 | offset: u64 | record_len: u32`
+const RECORD_SIZE: usize = 1 + 8 + 4 + 8 + 4;
+
+struct IndexRecord {
+    aggregate_id: Option<u64>,
+    segment_id: SegmentId,
+    offset: u64,
+    /// Total on-disk size of the data record this entry describes,
+    /// including its 4-byte length prefix.
+    record_len: u32,
+}
+
+impl IndexRecord {
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut buf = [0u8; RECORD_SIZE];
+        buf[0] = self.aggregate_id.is_some() as u8;
+        buf[1..9].copy_from_slice(&self.aggregate_id.unwrap_or(0).to_be_bytes());
+        buf[9..13].copy_from_slice(&self.segment_id.to_be_bytes());
+        buf[13..21].copy_from_slice(&self.offset.to_be_bytes());
+        buf[21..25].copy_from_slice(&self.record_len.to_be_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8; RECORD_SIZE]) -> IndexRecord {
+        let aggregate_id = u64::from_be_bytes(buf[1..9].try_into().unwrap());
+        IndexRecord {
+            aggregate_id: if buf[0] != 0 { Some(aggregate_id) } else { None },
+            segment_id: SegmentId::from_be_bytes(buf[9..13].try_into().unwrap()),
+            offset: u64::from_be_bytes(buf[13..21].try_into().unwrap()),
+            record_len: u32::from_be_bytes(buf[21..25].try_into().unwrap()),
+        }
+    }
+}
+
+/// The companion index file for a base log path, e.g. `accounts::Person.idx`
+/// next to `accounts::Person.0000000.log`.
+fn index_path(base_path: &Path) -> PathBuf {
+    let file_name = base_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .expect("base log path must have a file name");
+    let stem = file_name.strip_suffix(".log").unwrap_or(file_name);
+    let file_name = format!("{}.idx", stem);
+    match base_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.join(file_name),
+        _ => PathBuf::from(file_name),
+    }
+}
+
+/// Discards the on-disk index outright, for a caller about to rewrite the
+/// data log from scratch (e.g. [`crate::snapshot::compact`]) and repopulate
+/// it via fresh [`append_record`] calls rather than leave stale records
+/// from the log's previous contents mixed in.
+pub(crate) fn reset_index(base_path: &Path) -> io::Result<()> {
+    match fs::remove_file(index_path(base_path)) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Appends one index record, transactionally alongside the data write it
+/// describes. Called from [`crate::storage::append_stored_event`], the write
+/// path behind `append_event`, right after the data record is flushed.
+pub(crate) fn append_record(
+    base_path: &Path,
+    aggregate_id: Option<u64>,
+    segment_id: SegmentId,
+    offset: u64,
+    record_len: u32,
+) -> io::Result<()> {
+    let record = IndexRecord {
+        aggregate_id,
+        segment_id,
+        offset,
+        record_len,
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(index_path(base_path))?;
+    file.write_all(&record.encode())?;
+    file.flush()
+}
+
+/// Reads whole, well-formed records from the index file, discarding any
+/// trailing partial record left by a crash mid-write - the same "ignore
+/// trailing partial event" treatment the data log gets.
+fn read_records(path: &Path) -> io::Result<Vec<IndexRecord>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let mut records = Vec::with_capacity(bytes.len() / RECORD_SIZE);
+    for chunk in bytes.chunks_exact(RECORD_SIZE) {
+        records.push(IndexRecord::decode(chunk.try_into().unwrap()));
+    }
+    Ok(records)
+}
+
+/// True when the index no longer matches the data log: either its last
+/// record doesn't account for every byte currently in its segment (the log
+/// grew past what's indexed), or some record points at a segment that's
+/// since been pruned by [`crate::segment::prepare_active_segment`] - in
+/// which case every record, not just the last, could be stale, so this is
+/// the signal to fall back to a full rebuild rather than hand back
+/// locations into files that no longer exist.
+fn is_stale(base_path: &Path, records: &[IndexRecord]) -> io::Result<bool> {
+    let Some(last) = records.last() else {
+        return Ok(false);
+    };
+
+    let live_segments = list_segments(base_path)?;
+    if records.iter().any(|r| !live_segments.contains(&r.segment_id)) {
+        return Ok(true);
+    }
+
+    let implied_len = last.offset + last.record_len as u64;
+    let actual_len = match fs::metadata(segment_path(base_path, last.segment_id)) {
+        Ok(meta) => meta.len(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err),
+    };
+    Ok(actual_len != implied_len)
+}
+
+/// Returns the location of the single most recently appended record, or
+/// `None` if the index is missing, empty, or stale (in which case the
+/// caller should fall back to scanning the data log itself). An O(1)
+/// alternative to [`load_index`]'s full read for callers - like
+/// [`crate::storage::append_event`]'s hash-chaining - that only need the
+/// tail of the log, not every record in it.
+pub(crate) fn last_record(base_path: &Path) -> io::Result<Option<(SegmentId, u64)>> {
+    let mut file = match File::open(index_path(base_path)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let len = file.metadata()?.len();
+    let record_count = len / RECORD_SIZE as u64;
+    if record_count == 0 {
+        return Ok(None);
+    }
+
+    file.seek(SeekFrom::Start((record_count - 1) * RECORD_SIZE as u64))?;
+    let mut buf = [0u8; RECORD_SIZE];
+    file.read_exact(&mut buf)?;
+    let record = IndexRecord::decode(&buf);
+
+    if is_stale(base_path, std::slice::from_ref(&record))? {
+        return Ok(None);
+    }
+    Ok(Some((record.segment_id, record.offset)))
+}
+
+/// Rescans the whole data log and overwrites the index file with exactly
+/// the records implied by it, returning the resulting aggregate index.
+pub(crate) fn rebuild_and_persist(base_path: &Path) -> io::Result<AggregateIndex> {
+    let mut aggregate_index = AggregateIndex::new();
+    let mut records = Vec::new();
+
+    scan_log_entries(base_path, |segment_id, offset, len, event| {
+        let aggregate_id = event.event.aggregate_id;
+        if let Some(id) = aggregate_id {
+            aggregate_index.entry(id).or_default().push((segment_id, offset));
+        }
+        records.push(IndexRecord {
+            aggregate_id,
+            segment_id,
+            offset,
+            record_len: 4 + len,
+        });
+    })?;
+
+    let mut file = File::create(index_path(base_path))?;
+    for record in &records {
+        file.write_all(&record.encode())?;
+    }
+    file.flush()?;
+
+    Ok(aggregate_index)
+}
+
+/// Loads the aggregate index for `base_path`, the way a normal open should:
+/// read the on-disk index directly, and only fall back to a full rescan
+/// (rewriting the index file afterwards) when it is missing or stale.
+pub fn load_index<P: AsRef<Path>>(base_path: P) -> io::Result<AggregateIndex> {
+    let base_path = base_path.as_ref();
+    let records = read_records(&index_path(base_path))?;
+
+    if records.is_empty() || is_stale(base_path, &records)? {
+        return rebuild_and_persist(base_path);
+    }
+
+    let mut aggregate_index = AggregateIndex::new();
+    for record in records {
+        if let Some(aggregate_id) = record.aggregate_id {
+            aggregate_index
+                .entry(aggregate_id)
+                .or_default()
+                .push((record.segment_id, record.offset));
+        }
+    }
+    Ok(aggregate_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use crate::segment::RotateConfig;
+    use crate::storage::append_event;
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+
+    fn mock_event(id: u64) -> Event {
+        Event {
+            aggregate_id: Some(id),
+            schema_id: "Test".into(),
+            schema_version: 1,
+            namespace: "test".into(),
+            event_type: "Test".into(),
+            payload: json!({"name": "string"}),
+        }
+    }
+
+    #[test]
+    fn load_index_rebuilds_when_missing() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
+        fs::remove_file(index_path(path)).unwrap();
+
+        let index = load_index(path).unwrap();
+
+        assert_eq!(index.get(&1).unwrap().len(), 1);
+        assert_eq!(index.get(&2).unwrap().len(), 1);
+        assert!(index_path(path).exists());
+    }
+
+    #[test]
+    fn load_index_reads_straight_from_disk_when_fresh() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(1), &config).unwrap();
+
+        let index = load_index(path).unwrap();
+        assert_eq!(index.get(&1).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn load_index_detects_staleness_and_repairs() {
+        use crate::codec::{ActiveCodec, Codec};
+        use crate::event::StoredEvent;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+
+        // Simulate a crash right after a data write landed but before the
+        // matching index record was appended: write event 2's record
+        // straight to the active segment, bypassing `append_event`. Encoded
+        // with this build's codec, since that's what the segment's header
+        // already declares for record decoding.
+        let stored = StoredEvent {
+            write_timestamp_ms: 0,
+            prev_hash: crate::hash::ZERO_HASH,
+            event: mock_event(2),
+        };
+        let bytes = ActiveCodec::encode(&stored);
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(segment_path(path, 0))
+            .unwrap();
+        segment.write_all(&(bytes.len() as u32).to_be_bytes()).unwrap();
+        segment.write_all(&bytes).unwrap();
+        segment.flush().unwrap();
+
+        // The index still only accounts for event 1's bytes, so it no
+        // longer matches the segment's actual length.
+        let index = load_index(path).unwrap();
+        assert_eq!(index.get(&1).unwrap().len(), 1);
+        assert_eq!(index.get(&2).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn load_index_truncates_trailing_partial_record() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+
+        let mut index_file = OpenOptions::new()
+            .append(true)
+            .open(index_path(path))
+            .unwrap();
+        index_file.write_all(&[0u8; 5]).unwrap();
+
+        // The dangling partial record is discarded on read, and the whole
+        // record before it still accounts for the segment's full length, so
+        // the index is read straight from disk without any garbage in it.
+        let index = load_index(path).unwrap();
+        assert_eq!(index.get(&1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn load_index_drops_records_for_segments_pruned_since_they_were_indexed() {
+        use crate::storage::load_aggregate;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        // One event per segment, and only the newest segment survives -
+        // forces event 1's index record to outlive its segment file.
+        let config = RotateConfig {
+            max_bytes_per_segment: 1,
+            max_segment_count: 1,
+        };
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
+
+        // Without reconciling against the segments that actually survived
+        // pruning, the index's still-present record for event 1 would
+        // point at a deleted segment file.
+        let index = load_index(path).unwrap();
+        assert!(!index.contains_key(&1));
+        assert_eq!(index.get(&2).unwrap().len(), 1);
+
+        // And callers reading through that index don't hit a `NotFound`
+        // trying to open a segment that's gone.
+        assert!(load_aggregate(path, 1, &index).unwrap().is_empty());
+    }
+}
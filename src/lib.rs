@@ -0,0 +1,9 @@
+pub mod codec;
+pub mod event;
+pub mod hash;
+pub mod header;
+pub mod index_file;
+pub mod query;
+pub mod segment;
+pub mod snapshot;
+pub mod storage;
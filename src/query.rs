@@ -0,0 +1,262 @@
+//! Secondary indexes over namespace, schema, event type, aggregate and time,
+//! built in a single [`scan_log_entries`] pass, plus a [`query`] function
+//! that intersects them to answer filtered reads beyond single-aggregate
+//! lookups - e.g. "all `Created` events in namespace `accounts` for schema
+//! `Person` between two timestamps".
+//!
+//! Unlike [`crate::index_file`]'s on-disk aggregate index, these are held
+//! in memory only and rebuilt by [`build_indexes`] on demand: there isn't
+//! yet a case for paying to keep five indexes durable when one rescan
+//! builds them all together.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use crate::event::StoredEvent;
+use crate::storage::{read_locations, scan_log_entries, AggregateIndex, EventLocation};
+
+/// Secondary indexes over a log's events, keyed every way [`query`] can
+/// filter by. See [`build_indexes`].
+#[derive(Debug, Default)]
+pub struct Indexes {
+    /// Every event's location, in append order - the baseline set for a
+    /// query with no filters set, and what final results are sorted back
+    /// into after intersecting.
+    all: Vec<EventLocation>,
+    aggregate: AggregateIndex,
+    by_namespace: HashMap<String, Vec<EventLocation>>,
+    by_schema_id: HashMap<String, Vec<EventLocation>>,
+    by_event_type: HashMap<String, Vec<EventLocation>>,
+    /// Locations sorted by `write_timestamp_ms`, for range queries. Sorted
+    /// with a stable sort, so locations sharing a timestamp keep their
+    /// relative append order.
+    by_time: Vec<(u64, EventLocation)>,
+}
+
+/// A filter for [`query`]. Every set field narrows the result (logical AND,
+/// not OR); leaving every field `None` matches the whole log.
+#[derive(Debug, Clone, Default)]
+pub struct QueryFilter {
+    pub namespace: Option<String>,
+    pub schema_id: Option<String>,
+    pub event_type: Option<String>,
+    pub aggregate_id: Option<u64>,
+    /// Inclusive lower bound on `write_timestamp_ms`.
+    pub from_ms: Option<u64>,
+    /// Inclusive upper bound on `write_timestamp_ms`.
+    pub to_ms: Option<u64>,
+}
+
+/// Rebuilds every secondary index by rescanning the whole data log, at
+/// `O(total events)` cost, in the one pass [`scan_log_entries`] makes.
+pub fn build_indexes<P: AsRef<Path>>(base_path: P) -> io::Result<Indexes> {
+    let mut indexes = Indexes::default();
+
+    scan_log_entries(base_path, |segment_id, offset, _len, event| {
+        let location = (segment_id, offset);
+        indexes.all.push(location);
+        if let Some(aggregate_id) = event.event.aggregate_id {
+            indexes.aggregate.entry(aggregate_id).or_default().push(location);
+        }
+        indexes
+            .by_namespace
+            .entry(event.event.namespace.clone())
+            .or_default()
+            .push(location);
+        indexes
+            .by_schema_id
+            .entry(event.event.schema_id.clone())
+            .or_default()
+            .push(location);
+        indexes
+            .by_event_type
+            .entry(event.event.event_type.clone())
+            .or_default()
+            .push(location);
+        indexes.by_time.push((event.write_timestamp_ms, location));
+    })?;
+
+    indexes.by_time.sort_by_key(|(ts, _)| *ts);
+    Ok(indexes)
+}
+
+/// Narrows `current` to locations also present in `set`, or - the first
+/// time a filter field applies - just `set` itself.
+fn intersect(current: Option<HashSet<EventLocation>>, set: &[EventLocation]) -> Option<HashSet<EventLocation>> {
+    let set: HashSet<EventLocation> = set.iter().copied().collect();
+    Some(match current {
+        Some(current) => current.intersection(&set).copied().collect(),
+        None => set,
+    })
+}
+
+/// Answers `filter` against `indexes`, intersecting the relevant index
+/// sets and loading the matching [`StoredEvent`]s from the log, in append
+/// order.
+pub fn query<P: AsRef<Path>>(base_path: P, indexes: &Indexes, filter: &QueryFilter) -> io::Result<Vec<StoredEvent>> {
+    let mut candidates: Option<HashSet<EventLocation>> = None;
+
+    if let Some(namespace) = &filter.namespace {
+        let set = indexes.by_namespace.get(namespace).map(Vec::as_slice).unwrap_or(&[]);
+        candidates = intersect(candidates, set);
+    }
+    if let Some(schema_id) = &filter.schema_id {
+        let set = indexes.by_schema_id.get(schema_id).map(Vec::as_slice).unwrap_or(&[]);
+        candidates = intersect(candidates, set);
+    }
+    if let Some(event_type) = &filter.event_type {
+        let set = indexes.by_event_type.get(event_type).map(Vec::as_slice).unwrap_or(&[]);
+        candidates = intersect(candidates, set);
+    }
+    if let Some(aggregate_id) = filter.aggregate_id {
+        let set = indexes.aggregate.get(&aggregate_id).map(Vec::as_slice).unwrap_or(&[]);
+        candidates = intersect(candidates, set);
+    }
+    if filter.from_ms.is_some() || filter.to_ms.is_some() {
+        let from = filter.from_ms.unwrap_or(u64::MIN);
+        let to = filter.to_ms.unwrap_or(u64::MAX);
+        let start = indexes.by_time.partition_point(|(ts, _)| *ts < from);
+        let end = indexes.by_time.partition_point(|(ts, _)| *ts <= to);
+        let in_range: Vec<EventLocation> = indexes.by_time[start..end].iter().map(|(_, loc)| *loc).collect();
+        candidates = intersect(candidates, &in_range);
+    }
+
+    let matches: Vec<EventLocation> = match candidates {
+        Some(set) => indexes.all.iter().copied().filter(|loc| set.contains(loc)).collect(),
+        None => indexes.all.clone(),
+    };
+
+    read_locations(base_path, &matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use crate::segment::RotateConfig;
+    use crate::storage::append_event;
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+
+    fn mock_event(namespace: &str, schema_id: &str, event_type: &str, aggregate_id: Option<u64>) -> Event {
+        Event {
+            event_type: event_type.into(),
+            namespace: namespace.into(),
+            schema_id: schema_id.into(),
+            schema_version: 1,
+            aggregate_id,
+            payload: json!({"name": "string"}),
+        }
+    }
+
+    #[test]
+    fn query_with_no_filter_returns_everything_in_append_order() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(1)), &config).unwrap();
+        append_event(path, &mock_event("billing", "Invoice", "Created", Some(2)), &config).unwrap();
+
+        let indexes = build_indexes(path).unwrap();
+        let events = query(path, &indexes, &QueryFilter::default()).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.namespace, "accounts");
+        assert_eq!(events[1].event.namespace, "billing");
+    }
+
+    #[test]
+    fn query_intersects_namespace_schema_and_event_type() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(1)), &config).unwrap();
+        append_event(path, &mock_event("accounts", "Person", "Updated", Some(1)), &config).unwrap();
+        append_event(path, &mock_event("accounts", "Org", "Created", Some(2)), &config).unwrap();
+        append_event(path, &mock_event("billing", "Person", "Created", Some(3)), &config).unwrap();
+
+        let indexes = build_indexes(path).unwrap();
+        let filter = QueryFilter {
+            namespace: Some("accounts".into()),
+            schema_id: Some("Person".into()),
+            event_type: Some("Created".into()),
+            ..Default::default()
+        };
+        let events = query(path, &indexes, &filter).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event.aggregate_id, Some(1));
+    }
+
+    #[test]
+    fn query_filters_by_aggregate_id() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(1)), &config).unwrap();
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(2)), &config).unwrap();
+
+        let indexes = build_indexes(path).unwrap();
+        let filter = QueryFilter {
+            aggregate_id: Some(2),
+            ..Default::default()
+        };
+        let events = query(path, &indexes, &filter).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event.aggregate_id, Some(2));
+    }
+
+    #[test]
+    fn query_filters_by_timestamp_range() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(1)), &config).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let (_, mid_offset) = append_event(path, &mock_event("accounts", "Person", "Created", Some(2)), &config).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(3)), &config).unwrap();
+
+        let indexes = build_indexes(path).unwrap();
+        let mid_ts = indexes
+            .by_time
+            .iter()
+            .find(|(_, (_, offset))| *offset == mid_offset)
+            .unwrap()
+            .0;
+
+        let filter = QueryFilter {
+            from_ms: Some(mid_ts),
+            to_ms: Some(mid_ts),
+            ..Default::default()
+        };
+        let events = query(path, &indexes, &filter).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event.aggregate_id, Some(2));
+    }
+
+    #[test]
+    fn query_with_no_matches_returns_empty() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event("accounts", "Person", "Created", Some(1)), &config).unwrap();
+
+        let indexes = build_indexes(path).unwrap();
+        let filter = QueryFilter {
+            namespace: Some("nonexistent".into()),
+            ..Default::default()
+        };
+        let events = query(path, &indexes, &filter).unwrap();
+
+        assert!(events.is_empty());
+    }
+}
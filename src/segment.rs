@@ -0,0 +1,239 @@
+//! Segment naming and rotation bookkeeping for a size-bounded, multi-file log.
+//!
+//! A log at base path `accounts::Person.log` is stored as a sequence of
+//! sealed segment files `accounts::Person.0000000.log`,
+//! `accounts::Person.0000001.log`, ... Only the highest-numbered segment is
+//! ever appended to; once it would grow past `max_bytes_per_segment` it is
+//! left as-is ("sealed") and a new segment is opened.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Identifies a single segment file within a rotated log, in creation order.
+pub type SegmentId = u32;
+
+/// Rotation policy for a segmented log.
+#[derive(Debug, Clone, Copy)]
+pub struct RotateConfig {
+    /// Once the active segment would exceed this many bytes, it is sealed
+    /// and a new segment is started.
+    pub max_bytes_per_segment: u64,
+    /// Once more than this many segments exist, the oldest are dropped.
+    pub max_segment_count: usize,
+}
+
+impl Default for RotateConfig {
+    fn default() -> Self {
+        RotateConfig {
+            max_bytes_per_segment: 64 * 1024 * 1024,
+            max_segment_count: 16,
+        }
+    }
+}
+
+/// Strips the trailing `.log` off a base log path's file name, giving the
+/// stem that segment files are named after.
+fn stem(base_path: &Path) -> String {
+    let file_name = base_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .expect("base log path must have a file name");
+    file_name.strip_suffix(".log").unwrap_or(file_name).to_string()
+}
+
+fn dir_of(base_path: &Path) -> PathBuf {
+    match base_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Builds the on-disk path for a given segment, e.g. `accounts::Person.0000001.log`.
+pub fn segment_path(base_path: &Path, id: SegmentId) -> PathBuf {
+    dir_of(base_path).join(format!("{}.{:07}.log", stem(base_path), id))
+}
+
+/// Lists the segment IDs that currently exist for this base log, oldest first.
+pub fn list_segments(base_path: &Path) -> io::Result<Vec<SegmentId>> {
+    let dir = dir_of(base_path);
+    let prefix = format!("{}.", stem(base_path));
+
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut ids = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        let Some(name) = name.to_str() else { continue };
+        if let Some(id) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".log"))
+            .and_then(|digits| digits.parse::<SegmentId>().ok())
+        {
+            ids.push(id);
+        }
+    }
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+/// Picks the segment an append of `next_record_len` bytes should land in,
+/// sealing the current active segment in favor of a fresh one if it would
+/// push past `max_bytes_per_segment`, and prunes segments beyond
+/// `max_segment_count` in the process.
+pub fn prepare_active_segment(
+    base_path: &Path,
+    next_record_len: u64,
+    config: &RotateConfig,
+) -> io::Result<SegmentId> {
+    let mut segments = list_segments(base_path)?;
+    let active = *segments.last().unwrap_or(&0);
+    if segments.is_empty() {
+        segments.push(active);
+    }
+
+    let active_len = fs::metadata(segment_path(base_path, active))
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let target = if active_len > 0 && active_len + next_record_len > config.max_bytes_per_segment {
+        let next = active + 1;
+        segments.push(next);
+        next
+    } else {
+        active
+    };
+
+    prune_old_segments(base_path, &segments, config.max_segment_count)?;
+    Ok(target)
+}
+
+/// Removes every existing segment file for `base_path`, for a caller about
+/// to rewrite the log from scratch (e.g. [`crate::snapshot::compact`]) so
+/// the next [`prepare_active_segment`] call starts a clean segment 0 rather
+/// than appending after the log's previous contents.
+pub(crate) fn clear_segments(base_path: &Path) -> io::Result<()> {
+    for id in list_segments(base_path)? {
+        match fs::remove_file(segment_path(base_path, id)) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Drops the oldest sealed segments once there are more than `max_segment_count`.
+fn prune_old_segments(
+    base_path: &Path,
+    segments: &[SegmentId],
+    max_segment_count: usize,
+) -> io::Result<()> {
+    if segments.len() <= max_segment_count {
+        return Ok(());
+    }
+    let excess = segments.len() - max_segment_count;
+    for &id in &segments[..excess] {
+        match fs::remove_file(segment_path(base_path, id)) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    fn touch(path: &Path, len: u64) {
+        let file = File::create(path).unwrap();
+        file.set_len(len).unwrap();
+    }
+
+    #[test]
+    fn segment_path_inserts_zero_padded_id() {
+        let base = Path::new("/tmp/accounts::Person.log");
+        assert_eq!(
+            segment_path(base, 1),
+            Path::new("/tmp/accounts::Person.0000001.log")
+        );
+    }
+
+    #[test]
+    fn list_segments_is_empty_for_missing_directory_entries() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("accounts::Person.log");
+        assert_eq!(list_segments(&base).unwrap(), Vec::<SegmentId>::new());
+    }
+
+    #[test]
+    fn list_segments_returns_ids_in_order() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("accounts::Person.log");
+        touch(&segment_path(&base, 2), 0);
+        touch(&segment_path(&base, 0), 0);
+        touch(&segment_path(&base, 1), 0);
+
+        assert_eq!(list_segments(&base).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn prepare_active_segment_reuses_segment_with_room() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("accounts::Person.log");
+        let config = RotateConfig {
+            max_bytes_per_segment: 1024,
+            max_segment_count: 16,
+        };
+
+        touch(&segment_path(&base, 0), 10);
+
+        let target = prepare_active_segment(&base, 20, &config).unwrap();
+        assert_eq!(target, 0);
+    }
+
+    #[test]
+    fn prepare_active_segment_rotates_when_over_budget() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("accounts::Person.log");
+        let config = RotateConfig {
+            max_bytes_per_segment: 100,
+            max_segment_count: 16,
+        };
+
+        touch(&segment_path(&base, 0), 90);
+
+        let target = prepare_active_segment(&base, 20, &config).unwrap();
+        assert_eq!(target, 1);
+    }
+
+    #[test]
+    fn prepare_active_segment_prunes_oldest_past_max_segment_count() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("accounts::Person.log");
+        let config = RotateConfig {
+            max_bytes_per_segment: 100,
+            max_segment_count: 2,
+        };
+
+        touch(&segment_path(&base, 0), 0);
+        touch(&segment_path(&base, 1), 0);
+        touch(&segment_path(&base, 2), 90);
+
+        let target = prepare_active_segment(&base, 20, &config).unwrap();
+
+        // The new segment (3) is only a target id here; the caller creates
+        // it by actually writing to it. Pruning only touches segments that
+        // exist on disk, so 0 and 1 are dropped and 2 survives untouched.
+        assert_eq!(target, 3);
+        assert_eq!(list_segments(&base).unwrap(), vec![2]);
+    }
+}
@@ -0,0 +1,429 @@
+//! Aggregate snapshots, so replaying a long-lived aggregate via
+//! [`fold_aggregate`] doesn't cost one read per historical event: a
+//! snapshot captures folded state up to some point, and only events after
+//! it need replaying.
+//!
+//! Snapshots live in a sidecar `.snap` file next to the log, e.g.
+//! `accounts::Person.snap` beside `accounts::Person.0000000.log` - the same
+//! sidecar convention [`crate::index_file`] uses for the aggregate index.
+//! Unlike that index, there's no incremental append path: snapshots are
+//! only produced in bulk by [`compact`].
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::codec::Codec;
+use crate::event::StoredEvent;
+use crate::hash;
+use crate::segment::{self, RotateConfig};
+use crate::storage::{self, AggregateIndex, EventLocation};
+
+/// Folds one event onto an aggregate's accumulated state. Callers decide
+/// how an event's payload changes the aggregate - Chronicle doesn't
+/// interpret payloads itself, so the same log can be folded different ways
+/// by different reducers.
+pub type Reducer = fn(Value, &StoredEvent) -> Value;
+
+/// A persisted fold of one aggregate's events, up to and including the one
+/// at `up_to_offset`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub aggregate_id: u64,
+    /// The last event location, in the log's *current* coordinate space,
+    /// folded into `state` - or `None` when `state` already accounts for
+    /// every event the aggregate had, so any location the index has now is
+    /// necessarily newer and all of them need replaying.
+    ///
+    /// Physical `(segment_id, offset)` locations are not stable across a
+    /// [`compact`] call: it resets segment numbering, so a location from
+    /// before a compaction can collide with, or even sort before, one
+    /// written after it. `compact` sidesteps this by only ever producing
+    /// `None` here - it always drops every event it folds - but the field
+    /// stays an `Option` so a location can still anchor a cutoff in the
+    /// same log generation it was taken in (see the tests).
+    pub up_to_offset: Option<EventLocation>,
+    pub state: Value,
+    pub write_timestamp_ms: u64,
+}
+
+/// The sidecar snapshot file for a base log path, e.g.
+/// `accounts::Person.snap` next to `accounts::Person.0000000.log`.
+fn snapshot_path(base_path: &Path) -> PathBuf {
+    let file_name = base_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .expect("base log path must have a file name");
+    let stem = file_name.strip_suffix(".log").unwrap_or(file_name);
+    let file_name = format!("{}.snap", stem);
+    match base_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.join(file_name),
+        _ => PathBuf::from(file_name),
+    }
+}
+
+/// Reads every snapshot on disk, one JSON object per line, in the order
+/// they were written.
+fn read_snapshots(path: &Path) -> io::Result<Vec<Snapshot>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut snapshots = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        snapshots.push(serde_json::from_str(&line)?);
+    }
+    Ok(snapshots)
+}
+
+/// Overwrites the sidecar file with exactly `snapshots`, one per line -
+/// [`compact`] is the only writer, and it always rebuilds the full set.
+fn write_snapshots(path: &Path, snapshots: &[Snapshot]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    for snapshot in snapshots {
+        serde_json::to_writer(&mut file, snapshot)?;
+        file.write_all(b"\n")?;
+    }
+    file.flush()
+}
+
+/// Loads the latest snapshot for `aggregate_id`, if one exists. Later lines
+/// in the sidecar file supersede earlier ones for the same aggregate, the
+/// same way a later record wins in the data log.
+pub fn load_snapshot<P: AsRef<Path>>(base_path: P, aggregate_id: u64) -> io::Result<Option<Snapshot>> {
+    let snapshots = read_snapshots(&snapshot_path(base_path.as_ref()))?;
+    Ok(snapshots.into_iter().rfind(|s| s.aggregate_id == aggregate_id))
+}
+
+/// Folds `aggregate_id`'s events into current state with `reducer`, the way
+/// [`crate::storage::load_aggregate`] loads its raw events. Starts from the
+/// latest snapshot when one exists and replays only the locations after its
+/// `up_to_offset`, instead of every event the aggregate ever had.
+pub fn fold_aggregate<P: AsRef<Path>>(
+    base_path: P,
+    aggregate_id: u64,
+    index: &AggregateIndex,
+    reducer: Reducer,
+) -> io::Result<Value> {
+    let base_path = base_path.as_ref();
+    let locations = index.get(&aggregate_id).map(Vec::as_slice).unwrap_or(&[]);
+    let snapshot = load_snapshot(base_path, aggregate_id)?;
+
+    let (mut state, remaining) = match &snapshot {
+        Some(snapshot) => {
+            let remaining = match snapshot.up_to_offset {
+                Some(cutoff) => {
+                    let split = locations.partition_point(|loc| *loc <= cutoff);
+                    &locations[split..]
+                }
+                None => locations,
+            };
+            (snapshot.state.clone(), remaining)
+        }
+        None => (Value::Null, locations),
+    };
+
+    for event in storage::read_locations(base_path, remaining)? {
+        state = reducer(state, &event);
+    }
+    Ok(state)
+}
+
+/// What [`compact`] did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionReport {
+    /// How many aggregates got a fresh snapshot.
+    pub aggregates_snapshotted: usize,
+    /// Events dropped from the rewritten log because a fresh snapshot now
+    /// covers them.
+    pub events_dropped: u64,
+    /// Events carried over into the rewritten log untouched - aggregate-less
+    /// events (e.g. schema definitions), which no snapshot can cover.
+    pub events_retained: u64,
+}
+
+/// Snapshots every aggregate's full current state with `reducer`, then
+/// rewrites the data log keeping only the events no snapshot now covers -
+/// aggregate-less events (e.g. schema definitions), which nothing folds.
+/// Bounds log storage the way an archive-size cap bounds a diagnostics
+/// daemon's disk use: old detail is folded into a running total instead of
+/// kept forever.
+///
+/// The hash chain, segment files and on-disk aggregate index are rebuilt
+/// from scratch to match the rewritten log, and so are not skippable even
+/// when `reducer` drops nothing on a given run.
+pub fn compact<P: AsRef<Path>>(base_path: P, reducer: Reducer, config: &RotateConfig) -> io::Result<CompactionReport> {
+    let base_path = base_path.as_ref();
+    let index = crate::index_file::load_index(base_path)?;
+
+    let write_timestamp_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock before 1970")
+        .as_millis() as u64;
+
+    let mut snapshots = Vec::with_capacity(index.len());
+    for (&aggregate_id, locations) in &index {
+        if locations.is_empty() {
+            continue;
+        }
+        // Starts from any earlier snapshot, whose events an earlier
+        // compaction already dropped from the log.
+        let state = fold_aggregate(base_path, aggregate_id, &index, reducer)?;
+        snapshots.push(Snapshot {
+            aggregate_id,
+            up_to_offset: None,
+            state,
+            write_timestamp_ms,
+        });
+    }
+
+    // Every aggregate that got a fresh snapshot just had its whole history
+    // folded into it, so every one of its events is now superseded.
+    let snapshotted: HashSet<u64> = snapshots.iter().map(|s| s.aggregate_id).collect();
+
+    let mut retained = Vec::new();
+    let mut events_dropped = 0u64;
+    storage::scan_log_entries(base_path, |_segment_id, _offset, _len, event| {
+        let covered = event.event.aggregate_id.is_some_and(|id| snapshotted.contains(&id));
+        if covered {
+            events_dropped += 1;
+        } else {
+            retained.push(event);
+        }
+    })?;
+    let events_retained = retained.len() as u64;
+
+    segment::clear_segments(base_path)?;
+    crate::index_file::reset_index(base_path)?;
+
+    let mut prev_hash = hash::ZERO_HASH;
+    for mut event in retained {
+        event.prev_hash = prev_hash;
+        let encoded = crate::codec::ActiveCodec::encode(&event);
+        let len = encoded.len() as u32;
+        storage::append_stored_event(base_path, &event, config)?;
+        prev_hash = hash::compute_hash(&prev_hash, len, &encoded);
+    }
+
+    // Aggregates with no events left in the log keep their earlier snapshot.
+    let carried: Vec<Snapshot> = read_snapshots(&snapshot_path(base_path))?
+        .into_iter()
+        .filter(|s| !index.contains_key(&s.aggregate_id))
+        .collect();
+    write_snapshots(&snapshot_path(base_path), &[snapshots.as_slice(), carried.as_slice()].concat())?;
+
+    Ok(CompactionReport {
+        aggregates_snapshotted: snapshots.len(),
+        events_dropped,
+        events_retained,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use crate::storage::{append_event, load_aggregate, rebuild_index, verify_log};
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+
+    fn mock_event(id: u64, event_type: &str, name: &str) -> Event {
+        Event {
+            event_type: event_type.into(),
+            namespace: "accounts".into(),
+            schema_id: "Person".into(),
+            schema_version: 1,
+            aggregate_id: Some(id),
+            payload: json!({"name": name}),
+        }
+    }
+
+    fn reduce(state: Value, event: &StoredEvent) -> Value {
+        let mut map = match state {
+            Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        if let Value::Object(payload) = &event.event.payload {
+            for (key, value) in payload {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+        Value::Object(map)
+    }
+
+    #[test]
+    fn fold_aggregate_without_a_snapshot_replays_every_event() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+        append_event(path, &mock_event(1, "Updated", "Juan"), &config).unwrap();
+
+        let index = rebuild_index(path).unwrap();
+        let state = fold_aggregate(path, 1, &index, reduce).unwrap();
+
+        assert_eq!(state, json!({"name": "Juan"}));
+    }
+
+    #[test]
+    fn fold_aggregate_replays_only_events_past_the_snapshot() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        let (seg, off) = append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+        append_event(path, &mock_event(1, "Updated", "Juan"), &config).unwrap();
+
+        write_snapshots(
+            &snapshot_path(path),
+            &[Snapshot {
+                aggregate_id: 1,
+                up_to_offset: Some((seg, off)),
+                state: json!({"name": "Leandro", "stale_marker": true}),
+                write_timestamp_ms: 0,
+            }],
+        )
+        .unwrap();
+
+        let index = rebuild_index(path).unwrap();
+        let state = fold_aggregate(path, 1, &index, reduce).unwrap();
+
+        // `stale_marker` only appears in the snapshot, proving it was used
+        // as the starting state rather than rebuilt from event 1 onward.
+        assert_eq!(state, json!({"name": "Juan", "stale_marker": true}));
+    }
+
+    #[test]
+    fn compact_writes_one_snapshot_per_aggregate() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+        append_event(path, &mock_event(1, "Updated", "Juan"), &config).unwrap();
+        append_event(path, &mock_event(2, "Created", "Ada"), &config).unwrap();
+
+        let report = compact(path, reduce, &config).unwrap();
+
+        assert_eq!(report.aggregates_snapshotted, 2);
+        assert_eq!(load_snapshot(path, 1).unwrap().unwrap().state, json!({"name": "Juan"}));
+        assert_eq!(load_snapshot(path, 2).unwrap().unwrap().state, json!({"name": "Ada"}));
+    }
+
+    #[test]
+    fn compact_drops_events_now_covered_by_a_snapshot() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+        append_event(path, &mock_event(1, "Updated", "Juan"), &config).unwrap();
+
+        let report = compact(path, reduce, &config).unwrap();
+
+        assert_eq!(report.events_dropped, 2);
+        assert_eq!(report.events_retained, 0);
+
+        let index = rebuild_index(path).unwrap();
+        assert!(load_aggregate(path, 1, &index).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fold_aggregate_sees_events_appended_after_a_compaction() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+        append_event(path, &mock_event(1, "Updated", "Juan"), &config).unwrap();
+
+        compact(path, reduce, &config).unwrap();
+
+        // The rewritten log's segment/offset numbering restarts from
+        // scratch, so this event's location can legitimately sort *before*
+        // the pre-compaction locations the old snapshot used to reference.
+        append_event(path, &mock_event(1, "Updated", "Bob"), &config).unwrap();
+
+        let index = rebuild_index(path).unwrap();
+        let state = fold_aggregate(path, 1, &index, reduce).unwrap();
+
+        assert_eq!(state, json!({"name": "Bob"}));
+    }
+
+    #[test]
+    fn compact_keeps_aggregate_less_events() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        let schema_event = Event {
+            event_type: "SchemaDefined".into(),
+            namespace: "accounts".into(),
+            schema_id: "Person".into(),
+            schema_version: 1,
+            aggregate_id: None,
+            payload: json!({}),
+        };
+        append_event(path, &schema_event, &config).unwrap();
+        append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+
+        let report = compact(path, reduce, &config).unwrap();
+        assert_eq!(report.events_retained, 1);
+
+        let mut seen = Vec::new();
+        storage::scan_log_entries(path, |_, _, _, event| seen.push(event)).unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].event.event_type, "SchemaDefined");
+    }
+
+    #[test]
+    fn compacted_log_still_verifies() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1, "Created", "Leandro"), &config).unwrap();
+        append_event(path, &mock_event(1, "Updated", "Juan"), &config).unwrap();
+        append_event(path, &mock_event(2, "Created", "Ada"), &config).unwrap();
+
+        compact(path, reduce, &config).unwrap();
+
+        let report = verify_log(path).unwrap();
+        assert!(report.ok);
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_the_sidecar_file() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+
+        let snapshot = Snapshot {
+            aggregate_id: 7,
+            up_to_offset: Some((0, 16)),
+            state: json!({"balance": 42}),
+            write_timestamp_ms: 123,
+        };
+        write_snapshots(&snapshot_path(path), std::slice::from_ref(&snapshot)).unwrap();
+
+        assert_eq!(load_snapshot(path, 7).unwrap(), Some(snapshot));
+        assert_eq!(load_snapshot(path, 99).unwrap(), None);
+    }
+
+    #[test]
+    fn missing_snapshot_file_is_not_an_error() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        assert_eq!(load_snapshot(path, 1).unwrap(), None);
+    }
+}
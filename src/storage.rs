@@ -3,23 +3,48 @@ use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+use crate::codec::Codec;
 use crate::event::{Event, StoredEvent};
+use crate::hash::{self, Hash};
+use crate::header::{self, FormatVersion, HeaderFlags};
+use crate::segment::{list_segments, prepare_active_segment, segment_path, RotateConfig, SegmentId};
 
-/// Maps aggregate IDs to their event offsets in the log.
-pub type AggregateIndex = HashMap<u64, Vec<u64>>;
+/// A record's position in the log: segment plus in-segment byte offset.
+/// Byte offsets are only unique within a segment, so the segment ID travels
+/// alongside the offset.
+pub type EventLocation = (SegmentId, u64);
+
+/// Maps aggregate IDs to their locations in the log.
+pub type AggregateIndex = HashMap<u64, Vec<EventLocation>>;
 
 struct ReadResult {
     offset: u64,
+    /// Byte length of the encoded event, i.e. everything after the 4-byte
+    /// length prefix. `4 + len` is the total on-disk size of the record.
+    len: u32,
+    /// The raw encoded bytes, needed to recompute this record's chain hash.
+    json: Vec<u8>,
     event: StoredEvent,
 }
 
-fn open_file_for_read<P: AsRef<Path>>(path: P) -> Result<fs::File, io::Error> {
-    OpenOptions::new().read(true).open(path)
+/// Opens `path` for reading and parses its segment header, leaving the
+/// cursor positioned at the start of its first entry - right after the
+/// header for a v1 segment, or at byte 0 for a legacy headerless v0 one.
+fn open_file_for_read<P: AsRef<Path>>(path: P) -> Result<(fs::File, FormatVersion), io::Error> {
+    let mut file = OpenOptions::new().read(true).open(path)?;
+    let format = header::read_and_validate(&mut file)?;
+    Ok((file, format))
 }
 
 /// Reads the event at the current file cursor if offset is None,
-/// and advances the cursor to the start of the next entry.
-fn read_event_at_offset(file: &mut File, offset: Option<u64>) -> Result<ReadResult, io::Error> {
+/// and advances the cursor to the start of the next entry. `flags` picks
+/// the codec to decode with - the segment's own, from its header, not
+/// necessarily the one this build writes with.
+fn read_event_at_offset(
+    file: &mut File,
+    offset: Option<u64>,
+    flags: HeaderFlags,
+) -> Result<ReadResult, io::Error> {
     if let Some(off) = offset {
         file.seek(SeekFrom::Start(off))?;
     }
@@ -31,38 +56,99 @@ fn read_event_at_offset(file: &mut File, offset: Option<u64>) -> Result<ReadResu
     let mut event_buf = vec![0u8; length];
     file.read_exact(&mut event_buf)?;
 
+    let event = crate::codec::decode(flags, &event_buf)?;
     Ok(ReadResult {
         offset: returned_offset,
-        event: serde_json::from_slice(&event_buf)?,
+        len: length as u32,
+        json: event_buf,
+        event,
     })
 }
 
-fn scan_log_entries<P: AsRef<Path>, F: FnMut(u64, StoredEvent)>(
-    path: P,
+/// Walks every segment of `base_path`, oldest first, calling `f` with each
+/// entry's segment ID, in-segment offset and encoded byte length, so append
+/// order and timestamp ordering are preserved across segment boundaries.
+/// `pub(crate)` so the on-disk index can rebuild itself from the same pass
+/// without re-implementing segment traversal.
+pub(crate) fn scan_log_entries<P: AsRef<Path>, F: FnMut(SegmentId, u64, u32, StoredEvent)>(
+    base_path: P,
     mut f: F,
 ) -> Result<(), io::Error> {
-    let mut file = open_file_for_read(path)?;
-    loop {
-        let result = match read_event_at_offset(&mut file, None) {
-            Ok(r) => r,
-            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
-                // Clean EOF at entry boundary
-                break;
-            }
-            Err(err) => {
-                return Err(err);
-            }
+    let base_path = base_path.as_ref();
+    for segment_id in list_segments(base_path)? {
+        let (mut file, format) = match open_file_for_read(segment_path(base_path, segment_id)) {
+            Ok(opened) => opened,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
         };
-        f(result.offset, result.event);
+        let flags = format.flags();
+        loop {
+            let result = match read_event_at_offset(&mut file, None, flags) {
+                Ok(r) => r,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    // Clean EOF at entry boundary
+                    break;
+                }
+                Err(err) => {
+                    return Err(err);
+                }
+            };
+            f(segment_id, result.offset, result.len, result.event);
+        }
     }
     Ok(())
 }
 
-pub fn append_event<P: AsRef<Path>>(path: P, event: &Event) -> std::io::Result<u64> {
-    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+/// Finds the most recently written record in the log and recomputes its
+/// chain hash, or [`hash::ZERO_HASH`] if the log is empty. Reads the
+/// on-disk index's tail to jump straight to that record's location rather
+/// than rescanning a whole segment end-to-end on every single append, and
+/// only falls back to that full rescan when the index can't be trusted.
+fn last_hash(base_path: &Path) -> io::Result<Hash> {
+    if let Some((segment_id, offset)) = crate::index_file::last_record(base_path)? {
+        let (mut file, format) = match open_file_for_read(segment_path(base_path, segment_id)) {
+            Ok(opened) => opened,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(hash::ZERO_HASH),
+            Err(err) => return Err(err),
+        };
+        let result = read_event_at_offset(&mut file, Some(offset), format.flags())?;
+        return Ok(hash::compute_hash(&result.event.prev_hash, result.len, &result.json));
+    }
 
-    // Record offset before writing
-    let offset = file.seek(SeekFrom::End(0))?;
+    let Some(&active) = list_segments(base_path)?.last() else {
+        return Ok(hash::ZERO_HASH);
+    };
+    let (mut file, format) = match open_file_for_read(segment_path(base_path, active)) {
+        Ok(opened) => opened,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(hash::ZERO_HASH),
+        Err(err) => return Err(err),
+    };
+    let flags = format.flags();
+
+    let mut last: Option<ReadResult> = None;
+    loop {
+        match read_event_at_offset(&mut file, None, flags) {
+            Ok(result) => last = Some(result),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(match last {
+        Some(result) => hash::compute_hash(&result.event.prev_hash, result.len, &result.json),
+        None => hash::ZERO_HASH,
+    })
+}
+
+/// Appends `event` to the log rooted at `base_path`, rotating into a fresh
+/// segment first if the active one would grow past `config.max_bytes_per_segment`,
+/// and pruning segments beyond `config.max_segment_count`.
+pub fn append_event<P: AsRef<Path>>(
+    base_path: P,
+    event: &Event,
+    config: &RotateConfig,
+) -> std::io::Result<(SegmentId, u64)> {
+    let base_path = base_path.as_ref();
 
     // Chronicle sets the write timestamp
     let write_timestamp_ms = std::time::SystemTime::now()
@@ -70,70 +156,175 @@ pub fn append_event<P: AsRef<Path>>(path: P, event: &Event) -> std::io::Result<u
         .expect("system clock before 1970")
         .as_millis() as u64;
 
+    let prev_hash = last_hash(base_path)?;
+
     let stored_event = StoredEvent {
         write_timestamp_ms,
+        prev_hash,
         event: event.clone(),
     };
 
-    let json = serde_json::to_vec(&stored_event)?;
+    append_stored_event(base_path, &stored_event, config)
+}
+
+/// Appends an already-built [`StoredEvent`] verbatim - `append_event`'s
+/// machinery minus picking the timestamp and chaining off the log's current
+/// last hash, so a caller that already knows both (e.g. [`crate::snapshot::compact`]
+/// rebuilding a log from a known-good event sequence) can supply them itself.
+pub(crate) fn append_stored_event(
+    base_path: &Path,
+    stored_event: &StoredEvent,
+    config: &RotateConfig,
+) -> std::io::Result<(SegmentId, u64)> {
+    let json = crate::codec::ActiveCodec::encode(stored_event);
     let len = json.len() as u32;
+    let record_len = 4 + json.len() as u64;
+
+    let segment_id = prepare_active_segment(base_path, record_len, config)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(segment_path(base_path, segment_id))?;
+
+    // A brand-new segment file starts with a versioned header, written once
+    // here on its first append; every later append to the same segment just
+    // extends the entries after it.
+    if file.metadata()?.len() == 0 {
+        file.write_all(&header::encode(crate::codec::ActiveCodec::FLAGS))?;
+    }
+
+    // Record offset before writing
+    let offset = file.seek(SeekFrom::End(0))?;
 
     file.write_all(&len.to_be_bytes())?;
     file.write_all(&json)?;
     file.flush()?;
 
-    Ok(offset)
+    // Keep the on-disk index transactionally up to date with this write.
+    crate::index_file::append_record(
+        base_path,
+        stored_event.event.aggregate_id,
+        segment_id,
+        offset,
+        record_len as u32,
+    )?;
+
+    Ok((segment_id, offset))
+}
+
+/// Reads the events at each of `locations`, in the given order. Locations
+/// are typically append-ordered but may span several segments; only
+/// reopens a file when the segment actually changes.
+pub(crate) fn read_locations<P: AsRef<Path>>(
+    base_path: P,
+    locations: &[EventLocation],
+) -> Result<Vec<StoredEvent>, io::Error> {
+    let base_path = base_path.as_ref();
+    let mut results = Vec::with_capacity(locations.len());
+
+    let mut open: Option<(SegmentId, File, HeaderFlags)> = None;
+    for &(segment_id, offset) in locations {
+        if open.as_ref().map(|(id, ..)| *id) != Some(segment_id) {
+            let (file, format) = open_file_for_read(segment_path(base_path, segment_id))?;
+            open = Some((segment_id, file, format.flags()));
+        }
+        let (_, file, flags) = open.as_mut().expect("just opened above");
+        let result = read_event_at_offset(file, Some(offset), *flags)?;
+        results.push(result.event);
+    }
+
+    Ok(results)
 }
 
 pub fn load_aggregate<P: AsRef<Path>>(
-    path: P,
+    base_path: P,
     aggregate_id: u64,
     index: &AggregateIndex,
 ) -> Result<Vec<StoredEvent>, io::Error> {
-    let mut file = open_file_for_read(path)?;
-    let mut results = vec![];
-
-    let offsets = match index.get(&aggregate_id) {
-        Some(list) => list,
-        None => return Ok(vec![]),
-    };
-
-    for offset in offsets {
-        let result = read_event_at_offset(&mut file, Some(*offset))?;
-        results.push(result.event);
+    match index.get(&aggregate_id) {
+        Some(locations) => read_locations(base_path, locations),
+        None => Ok(vec![]),
     }
+}
 
-    Ok(results)
+/// Rebuilds the aggregate index by rescanning the whole data log, at
+/// `O(total events)` cost, and rewrites the on-disk index file to match.
+/// This is now a recovery/repair path only: normal opens should use
+/// [`crate::index_file::load_index`], which reads the on-disk index directly
+/// and falls back to this same rebuild only when that file is missing or
+/// stale.
+pub fn rebuild_index<P: AsRef<Path>>(base_path: P) -> Result<AggregateIndex, io::Error> {
+    crate::index_file::rebuild_and_persist(base_path.as_ref())
 }
 
-pub fn rebuild_index<P: AsRef<Path>>(path: P) -> Result<AggregateIndex, io::Error> {
-    let mut index = AggregateIndex::new();
-    scan_log_entries(path, |offset, event| {
-        if event.event.aggregate_id.is_some() {
-            index
-                .entry(event.event.aggregate_id.unwrap())
-                .or_default()
-                .push(offset);
-        }
-    })?;
-    Ok(index)
+pub fn read_events<P: AsRef<Path>>(base_path: P) -> Result<(), io::Error> {
+    scan_log_entries(base_path, |segment_id, offset, _len, event| {
+        println!("{:?} at segment {} offset {}", event, segment_id, offset);
+    })
 }
 
-pub fn read_events<P: AsRef<Path>>(path: P) -> Result<(), io::Error> {
-    let mut file = open_file_for_read(path)?;
-    loop {
-        let result = match read_event_at_offset(&mut file, None) {
-            Ok(r) => r,
-            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
-                // Clean EOF at entry boundary
-                return Ok(());
-            }
-            Err(err) => {
-                return Err(err);
-            }
+/// Outcome of [`verify_log`]: whether the hash chain held together, and if
+/// not, exactly where it first diverged.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub ok: bool,
+    /// Segment and offset of the first record whose `prev_hash` didn't
+    /// match the hash of the record before it. `None` when `ok` is true.
+    pub first_break: Option<(SegmentId, u64)>,
+    pub events_checked: u64,
+}
+
+/// Re-walks every segment of `base_path`, recomputing each record's chain
+/// hash and checking it links to the previous one, to catch tampering or
+/// silent corruption of a complete-but-altered record (a torn tail is
+/// already caught by the normal scan's partial-record handling).
+///
+/// The very first record encountered is trusted as the chain's anchor
+/// rather than required to carry [`hash::ZERO_HASH`]: once
+/// [`crate::segment::prune_old_segments`] has dropped the log's earliest
+/// segment, the oldest surviving record's `prev_hash` legitimately chains
+/// from history that's gone, and a log in that normal, post-pruning state
+/// must still verify. The tradeoff is that tampering with that one
+/// surviving anchor record's own `prev_hash` field goes undetected.
+pub fn verify_log<P: AsRef<Path>>(base_path: P) -> Result<VerifyReport, io::Error> {
+    let base_path = base_path.as_ref();
+    let mut expected_prev_hash: Option<Hash> = None;
+    let mut events_checked = 0u64;
+
+    for segment_id in list_segments(base_path)? {
+        let (mut file, format) = match open_file_for_read(segment_path(base_path, segment_id)) {
+            Ok(opened) => opened,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
         };
-        println!("{:?} at offset {}", result.event, result.offset);
+        let flags = format.flags();
+        loop {
+            let result = match read_event_at_offset(&mut file, None, flags) {
+                Ok(r) => r,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+
+            if let Some(expected) = expected_prev_hash {
+                if result.event.prev_hash != expected {
+                    return Ok(VerifyReport {
+                        ok: false,
+                        first_break: Some((segment_id, result.offset)),
+                        events_checked,
+                    });
+                }
+            }
+
+            expected_prev_hash = Some(hash::compute_hash(&result.event.prev_hash, result.len, &result.json));
+            events_checked += 1;
+        }
     }
+
+    Ok(VerifyReport {
+        ok: true,
+        first_break: None,
+        events_checked,
+    })
 }
 
 #[cfg(test)]
@@ -145,6 +336,7 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::segment::segment_path;
 
     fn mock_event(id: u64) -> Event {
         Event {
@@ -166,12 +358,13 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
         let e1 = mock_event(1);
         let e2 = mock_event(2);
 
-        let off1 = append_event(path, &e1).unwrap();
-        let off2 = append_event(path, &e2).unwrap();
+        let (_, off1) = append_event(path, &e1, &config).unwrap();
+        let (_, off2) = append_event(path, &e2, &config).unwrap();
 
         assert!(off2 > off1);
     }
@@ -183,7 +376,7 @@ mod tests {
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
         let mut seen = Vec::new();
-        scan_log_entries(&path, |offset, event| {
+        scan_log_entries(path, |_segment_id, offset, _len, event| {
             seen.push((offset, event));
         })
         .unwrap();
@@ -196,30 +389,25 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
         let ten_millis = time::Duration::from_millis(10);
 
-        let _ = append_event(path, &mock_event(1));
+        let _ = append_event(path, &mock_event(1), &config);
         sleep(ten_millis); // We sleep so we can be sure timestamps are different
-        let _ = append_event(path, &mock_event(1));
+        let _ = append_event(path, &mock_event(1), &config);
         sleep(ten_millis);
-        let _ = append_event(path, &mock_event(2));
+        let _ = append_event(path, &mock_event(2), &config);
 
         let mut seen = Vec::new();
 
-        scan_log_entries(&path, |offset, event| {
+        scan_log_entries(path, |_segment_id, offset, _len, event| {
             seen.push((offset, event));
         })
         .unwrap();
 
         assert_eq!(seen.len(), 3);
-        assert_eq!(
-            seen[0].1.write_timestamp_ms < seen[1].1.write_timestamp_ms,
-            true
-        );
-        assert_eq!(
-            seen[1].1.write_timestamp_ms < seen[2].1.write_timestamp_ms,
-            true
-        );
+        assert!(seen[0].1.write_timestamp_ms < seen[1].1.write_timestamp_ms);
+        assert!(seen[1].1.write_timestamp_ms < seen[2].1.write_timestamp_ms);
         assert_eq!(seen[2].1.event.aggregate_id, Some(2));
     }
 
@@ -229,18 +417,19 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
-        let _ = append_event(path, &mock_event(1));
-        let _ = append_event(path, &mock_event(1));
-        let _ = append_event(path, &mock_event(2));
+        let _ = append_event(path, &mock_event(1), &config);
+        let _ = append_event(path, &mock_event(1), &config);
+        let _ = append_event(path, &mock_event(2), &config);
 
-        let mut index: HashMap<u64, Vec<u64>> = HashMap::new();
+        let mut index: HashMap<u64, Vec<(SegmentId, u64)>> = HashMap::new();
 
-        scan_log_entries(&path, |offset, event| {
+        scan_log_entries(path, |segment_id, offset, _len, event| {
             index
                 .entry(event.event.aggregate_id.unwrap())
                 .or_default()
-                .push(offset);
+                .push((segment_id, offset));
         })
         .unwrap();
 
@@ -256,17 +445,21 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
         // Write two valid events
         let e1 = mock_event(1);
         let e2 = mock_event(1);
 
-        append_event(path, &e1).unwrap();
-        append_event(path, &e2).unwrap();
+        append_event(path, &e1, &config).unwrap();
+        append_event(path, &e2, &config).unwrap();
 
         // Manually corrupt the log:
         // write a length prefix but NOT the payload
-        let mut f = OpenOptions::new().append(true).open(path).unwrap();
+        let mut f = OpenOptions::new()
+            .append(true)
+            .open(segment_path(path, 0))
+            .unwrap();
         let bogus_len: u32 = 9999;
         f.write_all(&bogus_len.to_be_bytes()).unwrap();
         f.flush().unwrap();
@@ -274,7 +467,7 @@ mod tests {
         // seen should contain ONLY the valid events
         let mut seen = Vec::new();
 
-        scan_log_entries(&path, |offset, event| {
+        scan_log_entries(path, |_segment_id, offset, _len, event| {
             seen.push((offset, event));
         })
         .unwrap();
@@ -288,16 +481,18 @@ mod tests {
     fn scan_stops_cleanly_on_truncated_entry() {
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
-        let _ = append_event(path, &mock_event(1));
+        let _ = append_event(path, &mock_event(1), &config);
 
         // Truncate mid-entry
-        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        let segment = segment_path(path, 0);
+        let file = OpenOptions::new().write(true).open(&segment).unwrap();
         file.set_len(file.metadata().unwrap().len() - 3).unwrap();
 
         let mut count = 0;
 
-        scan_log_entries(&path, |_, _| {
+        scan_log_entries(path, |_, _, _, _| {
             count += 1;
         })
         .unwrap();
@@ -311,10 +506,11 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
-        append_event(path, &mock_event(1)).unwrap();
-        append_event(path, &mock_event(1)).unwrap();
-        append_event(path, &mock_event(2)).unwrap();
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
 
         let index = rebuild_index(path).unwrap();
 
@@ -328,14 +524,15 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
         let e1 = mock_event(1);
         let e2 = mock_event(1);
         let e3 = mock_event(2);
 
-        append_event(path, &e1).unwrap();
-        append_event(path, &e2).unwrap();
-        append_event(path, &e3).unwrap();
+        append_event(path, &e1, &config).unwrap();
+        append_event(path, &e2, &config).unwrap();
+        append_event(path, &e3, &config).unwrap();
 
         let index = rebuild_index(path).unwrap();
         let events = load_aggregate(path, 1, &index).unwrap();
@@ -349,14 +546,15 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
         let e1 = mock_event(1);
         let e2 = mock_event(1);
         let e3 = mock_event(1);
 
-        append_event(path, &e1).unwrap();
-        append_event(path, &e2).unwrap();
-        append_event(path, &e3).unwrap();
+        append_event(path, &e1, &config).unwrap();
+        append_event(path, &e2, &config).unwrap();
+        append_event(path, &e3, &config).unwrap();
 
         let index = rebuild_index(path).unwrap();
         let events = load_aggregate(path, 1, &index).unwrap();
@@ -376,11 +574,15 @@ mod tests {
 
         let file = NamedTempFile::new().unwrap();
         let path = file.path();
+        let config = RotateConfig::default();
 
-        append_event(path, &mock_event(1)).unwrap();
-        append_event(path, &mock_event(2)).unwrap();
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
 
-        let mut f = OpenOptions::new().append(true).open(path).unwrap();
+        let mut f = OpenOptions::new()
+            .append(true)
+            .open(segment_path(path, 0))
+            .unwrap();
         f.write_all(&1234u32.to_be_bytes()).unwrap();
 
         let index = rebuild_index(path).unwrap();
@@ -388,4 +590,225 @@ mod tests {
         assert_eq!(index.get(&1).unwrap().len(), 1);
         assert_eq!(index.get(&2).unwrap().len(), 1);
     }
+
+    #[test]
+    fn append_rotates_into_a_new_segment_past_the_byte_budget() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig {
+            max_bytes_per_segment: 1,
+            max_segment_count: 16,
+        };
+
+        let (seg1, _) = append_event(path, &mock_event(1), &config).unwrap();
+        let (seg2, _) = append_event(path, &mock_event(2), &config).unwrap();
+
+        assert_eq!(seg1, 0);
+        assert_eq!(seg2, 1);
+    }
+
+    #[test]
+    fn index_and_load_aggregate_span_segment_boundaries() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig {
+            max_bytes_per_segment: 1,
+            max_segment_count: 16,
+        };
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(1), &config).unwrap();
+
+        let index = rebuild_index(path).unwrap();
+        let locations = index.get(&1).unwrap();
+        assert_eq!(locations.iter().map(|(seg, _)| *seg).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let events = load_aggregate(path, 1, &index).unwrap();
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn old_segments_are_pruned_past_max_segment_count() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig {
+            max_bytes_per_segment: 1,
+            max_segment_count: 2,
+        };
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
+        append_event(path, &mock_event(3), &config).unwrap();
+
+        let index = rebuild_index(path).unwrap();
+
+        assert!(!index.contains_key(&1));
+        assert_eq!(index.get(&2).unwrap().len(), 1);
+        assert_eq!(index.get(&3).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn verify_log_is_ok_for_an_untampered_chain() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
+        append_event(path, &mock_event(3), &config).unwrap();
+
+        let report = verify_log(path).unwrap();
+
+        assert!(report.ok);
+        assert_eq!(report.first_break, None);
+        assert_eq!(report.events_checked, 3);
+    }
+
+    #[test]
+    fn verify_log_catches_a_tampered_payload() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        let (_, tampered_offset) = append_event(path, &mock_event(2), &config).unwrap();
+        let (_, next_offset) = append_event(path, &mock_event(3), &config).unwrap();
+
+        // Flip one byte inside event 2's `event_type` string, keeping the
+        // record's JSON structurally valid and the same byte length, so
+        // only its hash - not its parseability - is affected. Event 2's own
+        // `prev_hash` still checks out, so the chain only catches this once
+        // it reaches event 3, whose `prev_hash` no longer matches.
+        let segment = segment_path(path, 0);
+        let mut bytes = fs::read(&segment).unwrap();
+        let start = (tampered_offset + 4) as usize;
+        let rel = bytes[start..]
+            .windows(b"Test".len())
+            .position(|w| w == b"Test")
+            .unwrap();
+        bytes[start + rel] = b'X';
+        fs::write(&segment, &bytes).unwrap();
+
+        let report = verify_log(path).unwrap();
+
+        assert!(!report.ok);
+        assert_eq!(report.first_break, Some((0, next_offset)));
+    }
+
+    #[test]
+    fn append_chains_correctly_even_without_an_index_to_read_the_tail_from() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
+
+        // With no index file to consult, `last_hash` must fall back to
+        // rescanning the active segment directly and still find the real
+        // last record, not treat the log as empty.
+        crate::index_file::reset_index(path).unwrap();
+
+        append_event(path, &mock_event(3), &config).unwrap();
+
+        let report = verify_log(path).unwrap();
+        assert!(report.ok);
+        assert_eq!(report.events_checked, 3);
+    }
+
+    #[test]
+    fn verify_log_is_ok_after_pruning_drops_the_original_anchor() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig {
+            max_bytes_per_segment: 1,
+            max_segment_count: 2,
+        };
+
+        // One event per segment; pruning keeps only the two newest, so by
+        // the fourth append segments 0 and 1 are gone, leaving the oldest
+        // surviving record's prev_hash
+        // chained from a now-deleted record rather than ZERO_HASH.
+        append_event(path, &mock_event(1), &config).unwrap();
+        append_event(path, &mock_event(2), &config).unwrap();
+        append_event(path, &mock_event(3), &config).unwrap();
+        append_event(path, &mock_event(4), &config).unwrap();
+
+        assert_eq!(list_segments(path).unwrap(), vec![2, 3]);
+
+        let report = verify_log(path).unwrap();
+
+        assert!(report.ok);
+        assert_eq!(report.first_break, None);
+        assert_eq!(report.events_checked, 2);
+    }
+
+    #[test]
+    fn append_writes_a_chronlog_header_once_per_segment() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+        let config = RotateConfig::default();
+
+        let (seg, off1) = append_event(path, &mock_event(1), &config).unwrap();
+        let (_, off2) = append_event(path, &mock_event(2), &config).unwrap();
+
+        let bytes = fs::read(segment_path(path, seg)).unwrap();
+        assert_eq!(&bytes[0..crate::header::MAGIC.len()], crate::header::MAGIC);
+        // The header only precedes the first entry; the second append just
+        // extends the file, so offsets already account for it.
+        assert_eq!(off1, crate::header::HEADER_LEN as u64);
+        assert!(off2 > off1);
+    }
+
+    #[test]
+    fn reads_are_backward_compatible_with_headerless_v0_segments() {
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path();
+
+        // Hand-write a v0 segment: no header, just a bare length-prefixed
+        // record, the way segments looked before headers existed.
+        let stored = StoredEvent {
+            write_timestamp_ms: 0,
+            prev_hash: hash::ZERO_HASH,
+            event: mock_event(1),
+        };
+        let json = serde_json::to_vec(&stored).unwrap();
+        let mut segment = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(segment_path(path, 0))
+            .unwrap();
+        segment.write_all(&(json.len() as u32).to_be_bytes()).unwrap();
+        segment.write_all(&json).unwrap();
+        segment.flush().unwrap();
+
+        let mut seen = Vec::new();
+        scan_log_entries(path, |_segment_id, offset, _len, event| {
+            seen.push((offset, event));
+        })
+        .unwrap();
+
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, 0);
+        assert_eq!(seen[0].1.event.aggregate_id, Some(1));
+    }
 }